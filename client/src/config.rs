@@ -1,9 +1,17 @@
+use arc_swap::ArcSwap;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use getopts::{Fail, Options};
+use serde::{Deserialize, Serialize};
 use std::{
-    net::{AddrParseError, SocketAddr},
+    fs,
+    io::{self, Write},
+    net::{AddrParseError, IpAddr, SocketAddr},
     num::ParseIntError,
+    path::Path,
+    sync::Arc,
 };
 use thiserror::Error;
+use tuic::Address;
 
 pub struct ConfigBuilder<'cfg> {
     opts: Options,
@@ -14,31 +22,38 @@ impl<'cfg> ConfigBuilder<'cfg> {
     pub fn new() -> Self {
         let mut opts = Options::new();
 
-        opts.reqopt(
+        opts.optopt(
             "s",
             "server",
-            "Set the server address. This address is supposed to be in the certificate(Required)",
+            "Set the server address. This address is supposed to be in the certificate(Required, unless set in the config file)",
             "SERVER",
         );
-        opts.reqopt(
+        opts.optopt(
             "p",
             "server-port",
-            "Set the server port(Required)",
+            "Set the server port(Required, unless set in the config file)",
             "SERVER_PORT",
         );
-        opts.reqopt(
+        opts.optopt(
             "t",
             "token",
-            "Set the TUIC token for the server authentication(Required)",
+            "Set the TUIC token for the server authentication(Required, unless set in the config file)",
             "TOKEN",
         );
-        opts.reqopt(
+        opts.optopt(
             "l",
             "local-port",
-            "Set the listening port of the local socks5 server(Required)",
+            "Set the listening port of the local socks5 server(Required, unless set in the config file)",
             "LOCAL_PORT",
         );
 
+        opts.optopt(
+            "",
+            "config",
+            "Load settings from a TOML config file. CLI flags override individual values set in the file",
+            "CONFIG_FILE",
+        );
+
         opts.optopt(
             "",
             "server-ip",
@@ -53,6 +68,14 @@ impl<'cfg> ConfigBuilder<'cfg> {
             "NUMBER_OF_RETRIES",
         );
 
+        opts.optopt(
+            "",
+            "reassembly-timeout-secs",
+            "Set how long an incomplete reassembled UDP packet may sit idle before it's evicted \
+             (default: 30)",
+            "REASSEMBLY_TIMEOUT_SECS",
+        );
+
         opts.optopt(
             "",
             "socks5-username",
@@ -66,6 +89,14 @@ impl<'cfg> ConfigBuilder<'cfg> {
             "SOCKS5_PASSWORD",
         );
 
+        opts.optopt(
+            "",
+            "socks5-gssapi",
+            "Use GSSAPI instead of username/password for the local socks5 server authentication, \
+             against the given service name",
+            "SOCKS5_GSSAPI_SERVICE_NAME",
+        );
+
         opts.optopt(
             "",
             "cert",
@@ -73,12 +104,34 @@ impl<'cfg> ConfigBuilder<'cfg> {
             "CERTIFICATE",
         );
 
+        opts.optmulti(
+            "",
+            "pin-sha256",
+            "Pin the server certificate by the base64-encoded SHA-256 digest of its SPKI, bypassing \
+             normal CA chain validation (repeatable)",
+            "BASE64_DIGEST",
+        );
+
         opts.optflag(
             "",
             "allow-external-connection",
             "Allow external connections to the local socks5 server",
         );
 
+        opts.optmulti(
+            "",
+            "forward",
+            "Add a static port-forwarding rule instead of running the socks5 server: \
+             tcp|udp:LISTEN_ADDR:REMOTE_HOST:REMOTE_PORT (repeatable)",
+            "RULE",
+        );
+
+        opts.optflag(
+            "",
+            "wizard",
+            "Run an interactive wizard that prompts for the settings and writes them to a config file",
+        );
+
         opts.optflag("v", "version", "Print the version");
         opts.optflag("h", "help", "Print this help menu");
 
@@ -118,16 +171,52 @@ impl<'cfg> ConfigBuilder<'cfg> {
             return Err(ConfigError::Help(self.get_usage()));
         }
 
+        if matches.opt_present("wizard") {
+            return Err(self.run_wizard());
+        }
+
+        let file = match matches.opt_str("config") {
+            Some(path) => Some(self.load_config_file(path)?),
+            None => None,
+        };
+
         let server_addr = {
-            let server_name = matches.opt_str("s").unwrap();
+            let (server_name, file_server_port) = match matches.opt_str("s") {
+                Some(server_name) => {
+                    let file_server_port = file
+                        .as_ref()
+                        .and_then(|file| file.server_addr.as_deref())
+                        .and_then(|addr| addr.rsplit_once(':'))
+                        .map(|(_, port)| port.to_owned());
+
+                    (server_name, file_server_port)
+                }
+                None => {
+                    let addr = file
+                        .as_ref()
+                        .and_then(|file| file.server_addr.clone())
+                        .ok_or_else(|| ConfigError::MissingRequired("server", self.get_usage()))?;
+
+                    let (hostname, port) = addr
+                        .rsplit_once(':')
+                        .ok_or_else(|| ConfigError::MissingRequired("server-port", self.get_usage()))?;
+
+                    (hostname.to_owned(), Some(port.to_owned()))
+                }
+            };
 
             let server_port = matches
                 .opt_str("p")
-                .unwrap()
+                .or(file_server_port)
+                .ok_or_else(|| ConfigError::MissingRequired("server-port", self.get_usage()))?
                 .parse()
                 .map_err(|err| ConfigError::ParsePort(err, self.get_usage()))?;
 
-            if let Some(server_ip) = matches.opt_str("server-ip") {
+            let server_ip = matches
+                .opt_str("server-ip")
+                .or_else(|| file.as_ref().and_then(|file| file.server_ip.clone()));
+
+            if let Some(server_ip) = server_ip {
                 let server_ip = server_ip
                     .parse()
                     .map_err(|err| ConfigError::ParseServerIp(err, self.get_usage()))?;
@@ -147,65 +236,413 @@ impl<'cfg> ConfigBuilder<'cfg> {
         };
 
         let token = {
-            let token = matches.opt_str("t").unwrap();
+            let token = matches
+                .opt_str("t")
+                .or_else(|| file.as_ref().and_then(|file| file.token.clone()))
+                .ok_or_else(|| ConfigError::MissingRequired("token", self.get_usage()))?;
             seahash::hash(&token.into_bytes())
         };
 
-        let number_of_retries =
-            if let Some(number_of_retries) = matches.opt_str("number-of-retries") {
-                number_of_retries
+        let number_of_retries = match matches
+            .opt_str("number-of-retries")
+            .map(|s| s.parse().map_err(|err| ConfigError::ParseNumberOfRetries(err, self.get_usage())))
+            .transpose()?
+        {
+            Some(number_of_retries) => number_of_retries,
+            None => file
+                .as_ref()
+                .and_then(|file| file.number_of_retries)
+                .unwrap_or(3),
+        };
+
+        let reassembly_timeout_secs = match matches
+            .opt_str("reassembly-timeout-secs")
+            .map(|s| {
+                s.parse()
+                    .map_err(|err| ConfigError::ParseReassemblyTimeout(err, self.get_usage()))
+            })
+            .transpose()?
+        {
+            Some(reassembly_timeout_secs) => reassembly_timeout_secs,
+            None => file
+                .as_ref()
+                .and_then(|file| file.reassembly_timeout_secs)
+                .unwrap_or(30),
+        };
+
+        let local_addr = match matches.opt_str("l") {
+            Some(local_port) => {
+                let local_port = local_port
                     .parse()
-                    .map_err(|err| ConfigError::ParseNumberOfRetries(err, self.get_usage()))?
-            } else {
-                3
-            };
+                    .map_err(|err| ConfigError::ParsePort(err, self.get_usage()))?;
 
-        let local_addr = {
-            let local_port = matches
-                .opt_str("l")
-                .unwrap()
-                .parse()
-                .map_err(|err| ConfigError::ParsePort(err, self.get_usage()))?;
+                if matches.opt_present("allow-external-connection") {
+                    SocketAddr::from(([0, 0, 0, 0], local_port))
+                } else {
+                    SocketAddr::from(([127, 0, 0, 1], local_port))
+                }
+            }
+            None => {
+                let local_addr = file
+                    .as_ref()
+                    .and_then(|file| file.local_addr.clone())
+                    .ok_or_else(|| ConfigError::MissingRequired("local-port", self.get_usage()))?;
 
-            if matches.opt_present("allow-external-connection") {
-                SocketAddr::from(([0, 0, 0, 0], local_port))
-            } else {
-                SocketAddr::from(([127, 0, 0, 1], local_port))
+                local_addr
+                    .parse()
+                    .map_err(|err| ConfigError::ParseLocalAddr(err, self.get_usage()))?
             }
         };
 
-        let certificate_path = matches.opt_str("cert");
+        let forwards = matches
+            .opt_strs("forward")
+            .into_iter()
+            .map(|rule| parse_forward_rule(&rule, &self.get_usage()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let certificate_path = matches
+            .opt_str("cert")
+            .or_else(|| file.as_ref().and_then(|file| file.certificate_path.clone()));
+
+        let pin_sha256 = matches
+            .opt_strs("pin-sha256")
+            .into_iter()
+            .map(|pin| parse_pin(&pin, &self.get_usage()))
+            .collect::<Result<Vec<_>, _>>()?;
 
         let socks5_authentication = match (
             matches.opt_str("socks5-username"),
             matches.opt_str("socks5-password"),
+            matches.opt_str("socks5-gssapi"),
         ) {
-            (None, None) => Socks5AuthenticationConfig::None,
-            (Some(username), Some(password)) => Socks5AuthenticationConfig::Password {
+            (None, None, None) => file
+                .as_ref()
+                .and_then(|file| file.socks5_authentication.clone())
+                .map_or(Socks5AuthenticationConfig::None, |auth| {
+                    Socks5AuthenticationConfig::Password {
+                        username: auth.username.into_bytes(),
+                        password: auth.password.into_bytes(),
+                    }
+                }),
+            (Some(username), Some(password), None) => Socks5AuthenticationConfig::Password {
                 username: username.into_bytes(),
                 password: password.into_bytes(),
             },
+            (None, None, Some(service_name)) => Socks5AuthenticationConfig::GSSAPI { service_name },
+            (_, _, Some(_)) => return Err(ConfigError::Socks5GssapiAndPassword(self.get_usage())),
             _ => return Err(ConfigError::Socks5UsernameAndPassword(self.get_usage())),
         };
 
-        Ok(Config {
-            server_addr,
+        let reloadable = Arc::new(ArcSwap::new(Arc::new(ReloadableConfig {
             token,
             number_of_retries,
-            local_addr,
+            reassembly_timeout_secs,
             socks5_authentication,
             certificate_path,
+            pin_sha256,
+        })));
+
+        Ok(Config {
+            server_addr,
+            local_addr,
+            forwards,
+            reloadable,
         })
     }
+
+    /// Prompts the user step-by-step for the settings `parse` would otherwise
+    /// require as flags, validates each answer with the same rules `parse`
+    /// enforces, and writes the result out as a ready-to-use config file.
+    fn run_wizard(&self) -> ConfigError {
+        match self.run_wizard_inner() {
+            Ok(path) => ConfigError::WizardComplete(format!(
+                "Wrote config file to {path}\n\nRun again with --config {path}"
+            )),
+            Err(err) => ConfigError::Io(err),
+        }
+    }
+
+    fn run_wizard_inner(&self) -> io::Result<String> {
+        println!("TUIC client configuration wizard\n");
+
+        let server_addr = prompt_required("Server address (host:port)", |input| {
+            let (_, port) = input.rsplit_once(':')?;
+            port.parse::<u16>().ok().map(|_| input.to_owned())
+        })?;
+
+        let token = prompt_required("Token", |input| Some(input.to_owned()))?;
+
+        let local_addr = prompt_with_default(
+            "Local socks5 listening address",
+            "127.0.0.1:1080",
+            |input| input.parse::<SocketAddr>().ok().map(|addr| addr.to_string()),
+        )?;
+
+        let server_ip = prompt_optional("Server IP (overrides DNS lookup, optional)", |input| {
+            input.parse::<IpAddr>().ok().map(|ip| ip.to_string())
+        })?;
+
+        let certificate_path = prompt_optional("Custom certificate path (optional)", |input| {
+            Some(input.to_owned())
+        })?;
+
+        let socks5_authentication = loop {
+            let username = prompt_optional("Socks5 username (optional)", |input| {
+                Some(input.to_owned())
+            })?;
+            let password = prompt_optional("Socks5 password (optional)", |input| {
+                Some(input.to_owned())
+            })?;
+
+            match (username, password) {
+                (None, None) => break None,
+                (Some(username), Some(password)) => {
+                    break Some(Socks5AuthenticationConfigFile { username, password })
+                }
+                _ => println!("Socks5 username and password must be set together, try again"),
+            }
+        };
+
+        let number_of_retries = prompt_with_default("Number of retries", "3", |input| {
+            input.parse::<usize>().ok().map(|n| n.to_string())
+        })?
+        .parse()
+        .expect("validated by prompt_with_default");
+
+        let reassembly_timeout_secs = prompt_with_default(
+            "Reassembly timeout in seconds (for fragmented UDP packets)",
+            "30",
+            |input| input.parse::<u64>().ok().map(|n| n.to_string()),
+        )?
+        .parse()
+        .expect("validated by prompt_with_default");
+
+        let file = ConfigFile {
+            server_addr: Some(server_addr),
+            server_ip,
+            token: Some(token),
+            number_of_retries: Some(number_of_retries),
+            reassembly_timeout_secs: Some(reassembly_timeout_secs),
+            local_addr: Some(local_addr),
+            socks5_authentication,
+            certificate_path,
+        };
+
+        let output_path = prompt_with_default("Output path", "tuic-client.toml", |input| {
+            Some(input.to_owned())
+        })?;
+
+        let content = toml::to_string_pretty(&file).expect("ConfigFile always serializes");
+        fs::write(&output_path, content)?;
+
+        Ok(output_path)
+    }
+
+    fn load_config_file(&self, path: impl AsRef<Path>) -> Result<ConfigFile, ConfigError> {
+        let content = fs::read_to_string(path.as_ref())
+            .map_err(|err| ConfigError::ParseFile(err.to_string(), self.get_usage()))?;
+
+        toml::from_str(&content).map_err(|err| ConfigError::ParseFile(err.to_string(), self.get_usage()))
+    }
+}
+
+/// The shape of a `--config` TOML file: the server, token, retry, reassembly,
+/// local address, socks5 username/password and certificate settings of
+/// [`Config`], with everything optional so CLI flags can fill in the gaps.
+/// Forward rules, certificate pins and socks5 GSSAPI have no file fields and
+/// are set only by their flags.
+#[derive(Deserialize, Serialize, Default)]
+struct ConfigFile {
+    server_addr: Option<String>,
+    server_ip: Option<String>,
+    token: Option<String>,
+    number_of_retries: Option<usize>,
+    reassembly_timeout_secs: Option<u64>,
+    local_addr: Option<String>,
+    socks5_authentication: Option<Socks5AuthenticationConfigFile>,
+    certificate_path: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+struct Socks5AuthenticationConfigFile {
+    username: String,
+    password: String,
+}
+
+/// Prompts until `validate` accepts a non-empty answer.
+fn prompt_required(
+    prompt: &str,
+    validate: impl Fn(&str) -> Option<String>,
+) -> io::Result<String> {
+    loop {
+        let input = read_line(&format!("{prompt}: "))?;
+
+        if input.is_empty() {
+            println!("This field is required, try again");
+            continue;
+        }
+
+        match validate(&input) {
+            Some(value) => return Ok(value),
+            None => println!("Invalid input, try again"),
+        }
+    }
 }
 
+/// Prompts for an optional answer; an empty line means "skip".
+fn prompt_optional(
+    prompt: &str,
+    validate: impl Fn(&str) -> Option<String>,
+) -> io::Result<Option<String>> {
+    loop {
+        let input = read_line(&format!("{prompt}: "))?;
+
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        match validate(&input) {
+            Some(value) => return Ok(Some(value)),
+            None => println!("Invalid input, try again"),
+        }
+    }
+}
+
+/// Prompts for an answer, falling back to `default` on an empty line.
+fn prompt_with_default(
+    prompt: &str,
+    default: &str,
+    validate: impl Fn(&str) -> Option<String>,
+) -> io::Result<String> {
+    loop {
+        let input = read_line(&format!("{prompt} [{default}]: "))?;
+
+        let input = if input.is_empty() {
+            default.to_owned()
+        } else {
+            input
+        };
+
+        match validate(&input) {
+            Some(value) => return Ok(value),
+            None => println!("Invalid input, try again"),
+        }
+    }
+}
+
+fn read_line(prompt: &str) -> io::Result<String> {
+    print!("{prompt}");
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    let read = io::stdin().read_line(&mut input)?;
+
+    if read == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "stdin closed before the wizard finished",
+        ));
+    }
+
+    Ok(input.trim().to_owned())
+}
+
+/// The settings that stay fixed for the lifetime of the process: where the
+/// local socks5 server binds and how it reaches the remote server. Changing
+/// either requires restarting the client, since they're baked into the
+/// listener and the QUIC endpoint at startup.
 pub struct Config {
     pub server_addr: ServerAddr,
+    pub local_addr: SocketAddr,
+    pub forwards: Vec<ForwardRule>,
+    pub reloadable: Arc<ArcSwap<ReloadableConfig>>,
+}
+
+impl Config {
+    /// Re-runs the config parsing path against `args` and atomically swaps
+    /// in the freshly parsed reloadable settings. Existing streams keep
+    /// running against the old values; only new connections observe the
+    /// swap, since each reads `reloadable.load()` at the point of use.
+    pub fn reload(&self, args: &[String]) -> Result<(), ConfigError> {
+        let new = ConfigBuilder::new().parse(args)?;
+        self.reloadable.store(new.reloadable.load_full());
+        Ok(())
+    }
+}
+
+/// The settings that can be rotated without dropping live connections, e.g.
+/// to roll the TUIC token or swap a certificate. Held behind an `ArcSwap` so
+/// readers never block on a reload.
+pub struct ReloadableConfig {
     pub token: u64,
     pub number_of_retries: usize,
-    pub local_addr: SocketAddr,
+    /// How long, in seconds, an incomplete `Packet<side::Rx>` reassembly
+    /// entry may sit idle before `AssembleRx::evict_expired` drops it.
+    pub reassembly_timeout_secs: u64,
     pub socks5_authentication: Socks5AuthenticationConfig,
     pub certificate_path: Option<String>,
+    /// SHA-256 digests of pinned servers' SPKI. When non-empty, the QUIC
+    /// client verifier accepts a server iff its leaf certificate's SPKI
+    /// matches one of these, bypassing normal CA chain validation.
+    pub pin_sha256: Vec<[u8; 32]>,
+}
+
+fn parse_pin(pin: &str, usage: &str) -> Result<[u8; 32], ConfigError> {
+    let invalid = || ConfigError::ParsePin(pin.to_owned(), usage.to_owned());
+
+    let bytes = STANDARD.decode(pin).map_err(|_| invalid())?;
+
+    bytes.try_into().map_err(|_| invalid())
+}
+
+/// A single `--forward` rule: accept connections on `listen_addr` and tunnel
+/// them to `remote_addr` over TUIC, as either a `Connect` stream or a
+/// `Packet` association depending on `protocol`.
+#[derive(Clone)]
+pub struct ForwardRule {
+    pub protocol: ForwardProtocol,
+    pub listen_addr: SocketAddr,
+    pub remote_addr: Address,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ForwardProtocol {
+    Tcp,
+    Udp,
+}
+
+fn parse_forward_rule(rule: &str, usage: &str) -> Result<ForwardRule, ConfigError> {
+    let invalid = || ConfigError::ParseForwardRule(rule.to_owned(), usage.to_owned());
+
+    let mut parts = rule.splitn(4, ':');
+
+    let protocol = match parts.next().ok_or_else(invalid)? {
+        "tcp" => ForwardProtocol::Tcp,
+        "udp" => ForwardProtocol::Udp,
+        _ => return Err(invalid()),
+    };
+
+    let listen_host = parts.next().ok_or_else(invalid)?;
+    let listen_port = parts.next().ok_or_else(invalid)?;
+    let remote = parts.next().ok_or_else(invalid)?;
+
+    let listen_addr = format!("{listen_host}:{listen_port}")
+        .parse()
+        .map_err(|_| invalid())?;
+
+    let (remote_host, remote_port) = remote.rsplit_once(':').ok_or_else(invalid)?;
+    let remote_port = remote_port.parse().map_err(|_| invalid())?;
+
+    let remote_addr = match remote_host.parse() {
+        Ok(ip) => Address::SocketAddress(SocketAddr::new(ip, remote_port)),
+        Err(_) => Address::DomainAddress(remote_host.to_owned(), remote_port),
+    };
+
+    Ok(ForwardRule {
+        protocol,
+        listen_addr,
+        remote_addr,
+    })
 }
 
 #[derive(Clone)]
@@ -220,9 +657,20 @@ pub enum ServerAddr {
     },
 }
 
+impl ServerAddr {
+    /// The name the server's certificate is expected to be issued for,
+    /// whether or not DNS resolution was bypassed with `--server-ip`.
+    pub fn server_name(&self) -> &str {
+        match self {
+            Self::SocketAddr { server_name, .. } => server_name,
+            Self::HostnameAddr { hostname, .. } => hostname,
+        }
+    }
+}
+
 pub enum Socks5AuthenticationConfig {
     None,
-    // GSSAPI,
+    GSSAPI { service_name: String },
     Password {
         username: Vec<u8>,
         password: Vec<u8>,
@@ -241,8 +689,26 @@ pub enum ConfigError {
     ParseServerIp(AddrParseError, String),
     #[error("Failed to parse the number of retries: {0}\n\n{1}")]
     ParseNumberOfRetries(ParseIntError, String),
+    #[error("Failed to parse the reassembly timeout: {0}\n\n{1}")]
+    ParseReassemblyTimeout(ParseIntError, String),
+    #[error("Failed to parse the local address: {0}\n\n{1}")]
+    ParseLocalAddr(AddrParseError, String),
     #[error("Socks5 username and password must be set together\n\n{0}")]
     Socks5UsernameAndPassword(String),
+    #[error("Socks5 GSSAPI can't be combined with a socks5 username or password\n\n{0}")]
+    Socks5GssapiAndPassword(String),
+    #[error("Failed to load the config file: {0}\n\n{1}")]
+    ParseFile(String, String),
+    #[error("Failed to parse the forward rule: {0}\n\n{1}")]
+    ParseForwardRule(String, String),
+    #[error("Failed to parse the certificate pin {0} as a base64-encoded SHA-256 digest\n\n{1}")]
+    ParsePin(String, String),
+    #[error("Missing required option: {0}\n\n{1}")]
+    MissingRequired(&'static str, String),
+    #[error("{0}")]
+    WizardComplete(String),
+    #[error("{0}")]
+    Io(#[from] io::Error),
     #[error("{0}")]
     Version(&'static str),
     #[error("{0}")]
@@ -0,0 +1,139 @@
+use crate::config::{ForwardProtocol, ForwardRule};
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::copy_bidirectional,
+    net::{TcpListener, TcpStream, UdpSocket},
+};
+use tuic::Address;
+use tuic_quinn::Connection;
+
+/// Binds every configured `--forward` rule and, for each accepted
+/// connection, tunnels it over `connection` to the rule's `remote_addr`.
+/// Each rule runs in its own task so one misbehaving listener can't stall
+/// the others.
+pub async fn serve(connection: Connection, forwards: Vec<ForwardRule>) {
+    for rule in forwards {
+        let connection = connection.clone();
+
+        tokio::spawn(async move {
+            let result = match rule.protocol {
+                ForwardProtocol::Tcp => {
+                    serve_tcp(connection, rule.listen_addr, rule.remote_addr).await
+                }
+                ForwardProtocol::Udp => {
+                    serve_udp(connection, rule.listen_addr, rule.remote_addr).await
+                }
+            };
+
+            if let Err(err) = result {
+                log::error!("forward rule on {} failed: {err}", rule.listen_addr);
+            }
+        });
+    }
+}
+
+async fn serve_tcp(
+    connection: Connection,
+    listen_addr: SocketAddr,
+    remote_addr: Address,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(listen_addr).await?;
+    log::info!("forwarding tcp {listen_addr} -> {remote_addr}");
+
+    loop {
+        let (local, _) = listener.accept().await?;
+        let connection = connection.clone();
+        let remote_addr = remote_addr.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = forward_tcp_connection(connection, local, remote_addr).await {
+                log::warn!("tcp forward connection error: {err}");
+            }
+        });
+    }
+}
+
+async fn forward_tcp_connection(
+    connection: Connection,
+    mut local: TcpStream,
+    remote_addr: Address,
+) -> io::Result<()> {
+    let mut remote = connection
+        .connect(remote_addr)
+        .await
+        .map_err(io::Error::other)?;
+
+    copy_bidirectional(&mut local, &mut remote).await?;
+    Ok(())
+}
+
+/// Drives the UDP side of a forward rule: every datagram the local socket
+/// receives is split into `Packet`/`Fragment`s and sent under a single,
+/// stable `assoc_id` dedicated to this rule, mirroring the socks5 UDP
+/// associate path. A second task reassembles datagrams coming back from the
+/// server via `Connection::accept_packet` and writes them to the most
+/// recent local peer, so a rule like a DNS-over-UDP forward gets its answer
+/// back.
+async fn serve_udp(
+    connection: Connection,
+    listen_addr: SocketAddr,
+    remote_addr: Address,
+) -> io::Result<()> {
+    let socket = Arc::new(UdpSocket::bind(listen_addr).await?);
+    log::info!("forwarding udp {listen_addr} -> {remote_addr}");
+
+    let assoc_id = rand::random();
+    let last_peer = Arc::new(Mutex::new(None::<SocketAddr>));
+
+    let recv_task = tokio::spawn(relay_replies(
+        connection.clone(),
+        socket.clone(),
+        last_peer.clone(),
+    ));
+
+    let mut buf = vec![0u8; u16::MAX as usize];
+
+    let result = loop {
+        let (len, from) = match socket.recv_from(&mut buf).await {
+            Ok(ok) => ok,
+            Err(err) => break Err(err),
+        };
+
+        *last_peer.lock().unwrap() = Some(from);
+
+        if let Err(err) = connection
+            .packet(assoc_id, remote_addr.clone(), buf[..len].to_vec())
+            .await
+            .map_err(io::Error::other)
+        {
+            break Err(err);
+        }
+    };
+
+    recv_task.abort();
+    result
+}
+
+/// Reads reassembled datagrams off `connection` for as long as the rule
+/// runs, relaying each one to the most recently seen local peer. Replies
+/// that arrive before any local datagram has been sent are dropped, since
+/// there's no peer to deliver them to yet.
+async fn relay_replies(
+    connection: Connection,
+    socket: Arc<UdpSocket>,
+    last_peer: Arc<Mutex<Option<SocketAddr>>>,
+) -> io::Result<()> {
+    loop {
+        let (_, payload) = connection.accept_packet().await?;
+
+        let Some(peer) = *last_peer.lock().unwrap() else {
+            continue;
+        };
+
+        socket.send_to(&payload, peer).await?;
+    }
+}
@@ -0,0 +1,65 @@
+use crate::config::Config;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::sync::Arc;
+use tokio::signal::unix::{signal, SignalKind};
+
+/// Re-runs config parsing and atomically swaps in the result whenever the
+/// process receives SIGHUP, so the TUIC token, retry count, cert path or
+/// socks5 credentials can be rotated without dropping live connections.
+pub fn spawn_sighup_reload(config: Arc<Config>, args: Vec<String>) {
+    tokio::spawn(async move {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(err) => {
+                log::error!("failed to register SIGHUP handler: {err}");
+                return;
+            }
+        };
+
+        loop {
+            hangup.recv().await;
+            reload(&config, &args);
+        }
+    });
+}
+
+/// Watches `config_path` for modifications and reloads the same way SIGHUP
+/// does, for platforms or setups where sending a signal isn't convenient.
+pub fn spawn_file_watch_reload(config: Arc<Config>, args: Vec<String>, config_path: String) {
+    tokio::spawn(async move {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let mut watcher = match RecommendedWatcher::new(
+            move |res| {
+                let _ = tx.send(res);
+            },
+            notify::Config::default(),
+        ) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                log::error!("failed to create config file watcher: {err}");
+                return;
+            }
+        };
+
+        if let Err(err) = watcher.watch(config_path.as_ref(), RecursiveMode::NonRecursive) {
+            log::error!("failed to watch {config_path}: {err}");
+            return;
+        }
+
+        while let Some(event) = rx.recv().await {
+            match event {
+                Ok(event) if event.kind.is_modify() => reload(&config, &args),
+                Ok(_) => {}
+                Err(err) => log::error!("error watching {config_path}: {err}"),
+            }
+        }
+    });
+}
+
+fn reload(config: &Config, args: &[String]) {
+    match config.reload(args) {
+        Ok(()) => log::info!("reloaded configuration"),
+        Err(err) => log::error!("failed to reload configuration: {err}"),
+    }
+}
@@ -0,0 +1,111 @@
+pub mod gssapi;
+
+use crate::config::Socks5AuthenticationConfig;
+use gssapi::SecuredSession;
+use std::io::{Error, ErrorKind, Result};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// SOCKS5 authentication method identifiers, per RFC 1928 / RFC 1961.
+pub const METHOD_NONE: u8 = 0x00;
+pub const METHOD_GSSAPI: u8 = 0x01;
+pub const METHOD_PASSWORD: u8 = 0x02;
+const METHOD_NO_ACCEPTABLE: u8 = 0xff;
+
+const SOCKS5_VERSION: u8 = 0x05;
+
+/// What a connection authenticated as, once [`handshake`] returns
+/// successfully.
+pub enum Authenticated<'s, S> {
+    /// `METHOD_NONE` was negotiated.
+    None,
+    /// `METHOD_PASSWORD` was negotiated and the client's RFC 1929
+    /// credentials matched the configured ones.
+    Password,
+    /// `METHOD_GSSAPI` was negotiated; holds the [`SecuredSession`] that
+    /// wraps/unwraps the rest of the session per RFC 1961.
+    Gssapi(SecuredSession<'s, S>),
+}
+
+/// Runs the RFC 1928 method negotiation on a freshly accepted socks5
+/// connection, then dispatches to whichever method `auth` requires:
+/// `METHOD_GSSAPI` hands off to [`gssapi::accept`], `METHOD_PASSWORD` checks
+/// the RFC 1929 username/password sub-negotiation, and `METHOD_NONE`
+/// completes immediately. Fails if the client's offered method list doesn't
+/// include the one `auth` requires.
+pub async fn handshake<'s, S>(
+    stream: &'s mut S,
+    auth: &Socks5AuthenticationConfig,
+) -> Result<Authenticated<'s, S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header).await?;
+
+    if header[0] != SOCKS5_VERSION {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "unsupported socks version",
+        ));
+    }
+
+    let mut methods = vec![0u8; header[1] as usize];
+    stream.read_exact(&mut methods).await?;
+
+    let wanted = match auth {
+        Socks5AuthenticationConfig::None => METHOD_NONE,
+        Socks5AuthenticationConfig::Password { .. } => METHOD_PASSWORD,
+        Socks5AuthenticationConfig::GSSAPI { .. } => METHOD_GSSAPI,
+    };
+
+    if !methods.contains(&wanted) {
+        stream
+            .write_all(&[SOCKS5_VERSION, METHOD_NO_ACCEPTABLE])
+            .await?;
+
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "client did not offer the configured authentication method",
+        ));
+    }
+
+    stream.write_all(&[SOCKS5_VERSION, wanted]).await?;
+
+    match auth {
+        Socks5AuthenticationConfig::None => Ok(Authenticated::None),
+        Socks5AuthenticationConfig::Password { username, password } => {
+            check_password(stream, username, password).await?;
+            Ok(Authenticated::Password)
+        }
+        Socks5AuthenticationConfig::GSSAPI { service_name } => {
+            let session = gssapi::accept(stream, service_name).await?;
+            Ok(Authenticated::Gssapi(session))
+        }
+    }
+}
+
+/// Checks a client's RFC 1929 username/password sub-negotiation against the
+/// configured credentials.
+async fn check_password<S>(stream: &mut S, username: &[u8], password: &[u8]) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header).await?;
+
+    let mut given_username = vec![0u8; header[1] as usize];
+    stream.read_exact(&mut given_username).await?;
+
+    let password_len = stream.read_u8().await?;
+    let mut given_password = vec![0u8; password_len as usize];
+    stream.read_exact(&mut given_password).await?;
+
+    let ok = given_username == username && given_password == password;
+    stream.write_all(&[header[0], u8::from(!ok)]).await?;
+
+    if ok {
+        Ok(())
+    } else {
+        Err(Error::new(ErrorKind::PermissionDenied, "invalid credentials"))
+    }
+}
@@ -0,0 +1,91 @@
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    DigitallySignedStruct, Error as TlsError, SignatureScheme,
+};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use x509_parser::prelude::FromDer;
+
+/// A [`ServerCertVerifier`] that trusts a server iff the SHA-256 digest of
+/// its leaf certificate's SPKI (the DER-encoded `SubjectPublicKeyInfo`, not
+/// the whole certificate) matches one of `pins`, and the presented name
+/// matches `server_name`. Used in place of normal CA chain validation when
+/// `--pin-sha256` is set, so a self-hosted TUIC server's key can be trusted
+/// directly without a CA.
+#[derive(Debug)]
+pub struct SpkiVerifier {
+    pins: Vec<[u8; 32]>,
+    server_name: String,
+}
+
+impl SpkiVerifier {
+    pub fn new(pins: Vec<[u8; 32]>, server_name: String) -> Arc<Self> {
+        Arc::new(Self { pins, server_name })
+    }
+}
+
+impl ServerCertVerifier for SpkiVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, TlsError> {
+        if server_name.to_str() != self.server_name {
+            return Err(TlsError::General(format!(
+                "certificate presented for unexpected name: {server_name:?}"
+            )));
+        }
+
+        let (_, cert) = x509_parser::certificate::X509Certificate::from_der(end_entity)
+            .map_err(|err| TlsError::General(format!("failed to parse certificate: {err}")))?;
+
+        let spki = cert.tbs_certificate.subject_pki.raw;
+        let digest: [u8; 32] = Sha256::digest(spki).into();
+
+        if self.pins.iter().any(|pin| *pin == digest) {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(TlsError::General(
+                "certificate SPKI does not match any pinned digest".to_owned(),
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TlsError> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &rustls::crypto::ring::default_provider().signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TlsError> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &rustls::crypto::ring::default_provider().signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        rustls::crypto::ring::default_provider()
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
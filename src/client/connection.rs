@@ -0,0 +1,110 @@
+use super::stream::{RecvStream, SendStream, Stream, StreamReg};
+use std::{io, time::Duration};
+use tokio::io::AsyncWriteExt;
+use tuic::{
+    protocol::{Connect as ConnectHeader, Header},
+    prototype::{
+        packet::{AssembleRx, Packet},
+        side,
+    },
+    Address,
+};
+
+/// The client-side handle to a single QUIC connection to the TUIC server.
+/// Wraps the raw `quinn::Connection` with the bits every caller needs: a
+/// `StreamReg` so open streams can be tracked for graceful shutdown, and an
+/// `AssembleRx` so the datagram receive loop can reassemble fragmented UDP
+/// packets before handing them back out (e.g. to a `--forward udp:...`
+/// rule).
+#[derive(Clone)]
+pub struct Connection {
+    conn: quinn::Connection,
+    reg: StreamReg,
+    assemble_rx: AssembleRx,
+}
+
+impl Connection {
+    /// `reassembly_timeout` comes from `ReloadableConfig::reassembly_timeout_secs`,
+    /// so an operator can tune how long an incomplete `Packet<side::Rx>`
+    /// entry may sit idle before it's evicted.
+    pub fn new(conn: quinn::Connection, reassembly_timeout: Duration) -> Self {
+        let this = Self {
+            conn,
+            reg: StreamReg::default(),
+            assemble_rx: AssembleRx::new(),
+        };
+
+        this.spawn_reassembly_evictor(reassembly_timeout);
+        this
+    }
+
+    /// Periodically drops reassembly entries that haven't seen a new
+    /// fragment within `timeout`, so a datagram that permanently lost a
+    /// fragment can't hold memory for the lifetime of the connection. One
+    /// evictor task runs per `Connection`; since a client holds at most one
+    /// `Connection` to its server at a time, this is bounded.
+    fn spawn_reassembly_evictor(&self, timeout: Duration) {
+        let assemble_rx = self.assemble_rx.clone();
+
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(timeout).await;
+                assemble_rx.evict_expired(timeout);
+            }
+        });
+    }
+
+    pub async fn connect(&self, addr: Address) -> io::Result<Stream> {
+        let (send, recv) = self.conn.open_bi().await.map_err(io::Error::other)?;
+
+        let mut stream = Stream::new(
+            SendStream::new(send, self.reg.clone()),
+            RecvStream::new(recv, self.reg.clone()),
+        );
+
+        let header = Header::Connect(ConnectHeader::new(addr)).encode();
+        stream.write_all(&header).await?;
+
+        Ok(stream)
+    }
+
+    pub async fn packet(&self, assoc_id: u16, addr: Address, payload: Vec<u8>) -> io::Result<()> {
+        let pkt_id = rand::random();
+        let max_pkt_size = self.conn.max_datagram_size().unwrap_or(1200);
+
+        for (header, payload) in Packet::<side::Tx>::new(assoc_id, pkt_id, addr, max_pkt_size)
+            .into_fragments(&payload)
+        {
+            let mut datagram = header.encode();
+            datagram.extend_from_slice(payload);
+
+            self.conn
+                .send_datagram(datagram.into())
+                .map_err(io::Error::other)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads and reassembles incoming `Packet` datagrams until a complete
+    /// one is available, skipping any `Connect`-framed datagram (which
+    /// shouldn't arrive over the unreliable datagram channel) and any
+    /// fragment that doesn't yet complete a datagram.
+    pub async fn accept_packet(&self) -> io::Result<(Address, Vec<u8>)> {
+        loop {
+            let datagram = self.conn.read_datagram().await.map_err(io::Error::other)?;
+
+            let Header::Packet(header) = Header::decode(&datagram).map_err(io::Error::other)? else {
+                continue;
+            };
+
+            let payload = &datagram[header.len()..];
+
+            if let Some(completed) =
+                Packet::<side::Rx>::new(self.assemble_rx.clone()).assemble(&header, payload)
+            {
+                return Ok(completed);
+            }
+        }
+    }
+}
@@ -1,5 +1,10 @@
 use super::side::{self, Side, SideMarker};
 use crate::protocol::{Address, Header, Packet as PacketHeader};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 pub struct Packet<M>
 where
@@ -16,7 +21,131 @@ pub struct Tx {
     max_pkt_size: usize,
 }
 
-pub struct Rx;
+pub struct Rx {
+    assemble: AssembleRx,
+}
+
+impl Packet<side::Rx> {
+    /// Public (unlike `Packet::<side::Tx>::new`) because, unlike the TX
+    /// side, there is no in-crate connection facade yet to broker
+    /// construction for callers driving the QUIC datagram receive loop
+    /// (e.g. the client's port-forwarding code).
+    pub fn new(assemble: AssembleRx) -> Self {
+        Self {
+            inner: Side::Rx(Rx { assemble }),
+            _marker: side::Rx,
+        }
+    }
+
+    /// Feeds one received fragment into the reassembly buffer, returning
+    /// the completed datagram once every fragment of its `pkt_id` has
+    /// arrived.
+    pub fn assemble(&self, header: &PacketHeader, payload: &[u8]) -> Option<(Address, Vec<u8>)> {
+        let Side::Rx(rx) = &self.inner else { unreachable!() };
+        rx.assemble.insert(header, payload)
+    }
+}
+
+/// A keyed buffer of in-progress `(assoc_id, pkt_id)` datagrams, collecting
+/// fragments as they arrive and yielding the completed payload once
+/// `frag_total` of them are present. Cloning shares the same underlying
+/// buffer, mirroring the `StreamReg`-style shared-handle pattern used
+/// elsewhere for per-connection state.
+#[derive(Clone)]
+pub struct AssembleRx(Arc<Mutex<HashMap<(u16, u16), Entry>>>);
+
+struct Entry {
+    addr: Address,
+    frag_total: u8,
+    received: u8,
+    fragments: Vec<Option<Vec<u8>>>,
+    size: usize,
+    last_active: Instant,
+}
+
+impl AssembleRx {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(HashMap::new())))
+    }
+
+    /// Inserts a fragment, returning the reassembled `(Address, Vec<u8>)`
+    /// once `header.frag_total()` fragments have been collected for its
+    /// `(assoc_id, pkt_id)`.
+    pub fn insert(&self, header: &PacketHeader, payload: &[u8]) -> Option<(Address, Vec<u8>)> {
+        if header.frag_id() >= header.frag_total() {
+            return None;
+        }
+
+        let mut buf = self.0.lock().unwrap();
+
+        let key = (header.assoc_id(), header.pkt_id());
+        let frag_id = header.frag_id() as usize;
+
+        let entry = buf.entry(key).or_insert_with(|| Entry {
+            addr: Address::None,
+            frag_total: header.frag_total(),
+            received: 0,
+            fragments: vec![None; header.frag_total() as usize],
+            size: 0,
+            last_active: Instant::now(),
+        });
+
+        // A peer that changes frag_total mid-stream for the same
+        // (assoc_id, pkt_id) is malformed or malicious; drop the fragment
+        // rather than index out of the bounds established by the first one.
+        if header.frag_total() != entry.frag_total || frag_id >= entry.fragments.len() {
+            return None;
+        }
+
+        // Only a genuinely new fragment counts as activity: bumping
+        // last_active on a duplicate/retransmitted fragment would let a peer
+        // that withholds one fragment keep resending an already-received one
+        // to pin the entry in memory forever, defeating eviction.
+        if entry.fragments[frag_id].is_none() {
+            entry.last_active = Instant::now();
+
+            // Guard the address the same way the payload is guarded below:
+            // only the first arrival of fragment 0 may set it, so a
+            // duplicate or spoofed retransmission of fragment 0 can't
+            // redirect an already-in-progress datagram to a different
+            // address.
+            if frag_id == 0 {
+                entry.addr = header.addr().clone();
+            }
+
+            entry.size += payload.len();
+            entry.fragments[frag_id] = Some(payload.to_vec());
+            entry.received += 1;
+        }
+
+        if entry.received < entry.frag_total {
+            return None;
+        }
+
+        let entry = buf.remove(&key).unwrap();
+        let mut datagram = Vec::with_capacity(entry.size);
+
+        for fragment in entry.fragments {
+            datagram.extend_from_slice(&fragment.expect("all fragments present"));
+        }
+
+        Some((entry.addr, datagram))
+    }
+
+    /// Drops any entry that hasn't received a new fragment within `timeout`,
+    /// so a permanently lost fragment can't hold its partial datagram in
+    /// memory forever.
+    pub fn evict_expired(&self, timeout: Duration) {
+        let mut buf = self.0.lock().unwrap();
+        buf.retain(|_, entry| entry.last_active.elapsed() < timeout);
+    }
+}
+
+impl Default for AssembleRx {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Packet<side::Tx> {
     pub(super) fn new(assoc_id: u16, pkt_id: u16, addr: Address, max_pkt_size: usize) -> Self {
@@ -113,4 +242,124 @@ impl ExactSizeIterator for Fragment<'_> {
     fn len(&self) -> usize {
         self.frag_total as usize
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(pkt_id: u16, frag_total: u8, frag_id: u8, size: u16, addr: Address) -> PacketHeader {
+        PacketHeader::new(1, pkt_id, frag_total, frag_id, size, addr)
+    }
+
+    #[test]
+    fn reassembles_in_order_fragments() {
+        let assemble = AssembleRx::new();
+        let addr = Address::DomainAddress("example.com".to_owned(), 53);
+
+        assert!(assemble
+            .insert(&header(1, 2, 0, 3, addr.clone()), b"foo")
+            .is_none());
+
+        let (got_addr, payload) = assemble
+            .insert(&header(1, 2, 1, 3, Address::None), b"bar")
+            .unwrap();
+
+        assert_eq!(got_addr, addr);
+        assert_eq!(payload, b"foobar");
+    }
+
+    #[test]
+    fn reassembles_out_of_order_fragments() {
+        let assemble = AssembleRx::new();
+        let addr = Address::DomainAddress("example.com".to_owned(), 53);
+
+        assert!(assemble
+            .insert(&header(2, 2, 1, 3, Address::None), b"bar")
+            .is_none());
+
+        let (got_addr, payload) = assemble
+            .insert(&header(2, 2, 0, 3, addr.clone()), b"foo")
+            .unwrap();
+
+        assert_eq!(got_addr, addr);
+        assert_eq!(payload, b"foobar");
+    }
+
+    #[test]
+    fn drops_fragment_with_mismatched_frag_total() {
+        let assemble = AssembleRx::new();
+        let addr = Address::DomainAddress("example.com".to_owned(), 53);
+
+        assert!(assemble.insert(&header(3, 2, 0, 3, addr), b"foo").is_none());
+
+        // A later fragment claiming a different frag_total for the same
+        // (assoc_id, pkt_id) is malformed/malicious and must be dropped
+        // rather than indexed into the original-sized fragment buffer.
+        assert!(assemble
+            .insert(&header(3, 3, 1, 3, Address::None), b"bar")
+            .is_none());
+    }
+
+    #[test]
+    fn duplicate_fragment_zero_does_not_overwrite_addr() {
+        let assemble = AssembleRx::new();
+        let real_addr = Address::DomainAddress("example.com".to_owned(), 53);
+        let spoofed_addr = Address::DomainAddress("evil.example".to_owned(), 53);
+
+        assert!(assemble
+            .insert(&header(4, 2, 0, 3, real_addr.clone()), b"foo")
+            .is_none());
+
+        // A duplicate/spoofed retransmission of fragment 0 must not replace
+        // the address recorded on first arrival.
+        assert!(assemble
+            .insert(&header(4, 2, 0, 3, spoofed_addr), b"xxx")
+            .is_none());
+
+        let (got_addr, payload) = assemble
+            .insert(&header(4, 2, 1, 3, Address::None), b"bar")
+            .unwrap();
+
+        assert_eq!(got_addr, real_addr);
+        assert_eq!(payload, b"foobar");
+    }
+
+    #[test]
+    fn eviction_ignores_duplicate_driven_activity() {
+        let assemble = AssembleRx::new();
+        let addr = Address::DomainAddress("example.com".to_owned(), 53);
+
+        assemble.insert(&header(5, 2, 0, 3, addr.clone()), b"foo");
+        std::thread::sleep(Duration::from_millis(20));
+
+        // Resending an already-received fragment must not refresh
+        // last_active, or a peer could withhold one fragment forever while
+        // pinning the entry in memory by replaying this one.
+        assemble.insert(&header(5, 2, 0, 3, addr), b"foo");
+
+        assemble.evict_expired(Duration::from_millis(10));
+
+        assert_eq!(assemble.0.lock().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn eviction_spares_entries_with_genuinely_new_fragments() {
+        let assemble = AssembleRx::new();
+        let addr = Address::DomainAddress("example.com".to_owned(), 53);
+
+        // frag_total is 3 so the entry stays incomplete (and thus in the
+        // map) after the second insert below.
+        assemble.insert(&header(6, 3, 0, 3, addr), b"foo");
+        std::thread::sleep(Duration::from_millis(20));
+
+        // A genuinely new fragment refreshes last_active, so the entry
+        // should survive a sweep whose timeout is shorter than the sleep
+        // above but longer than the time since this insert.
+        assemble.insert(&header(6, 3, 1, 3, Address::None), b"bar");
+
+        assemble.evict_expired(Duration::from_millis(10));
+
+        assert_eq!(assemble.0.lock().unwrap().len(), 1);
+    }
 }
\ No newline at end of file